@@ -0,0 +1,181 @@
+//! An owning, dereferenceable tagged pointer.
+
+#![cfg(feature = "alloc")]
+
+use alloc::boxed::Box;
+use core::fmt;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::ptr::NonNull;
+
+use crate::MarkedNonNull;
+
+/// An owning pointer type that stores a heap-allocated `T` together with `N`
+/// bits of tag.
+///
+/// Unlike [`MarkedPtr`][crate::MarkedPtr] and [`MarkedNonNull`], which are
+/// `Copy` views into a pointer and carry no ownership semantics, a
+/// `MarkedBox` owns its pointee: it [`Deref`]s (and [`DerefMut`]s) to `T`
+/// with the tag bits transparently stripped out of the reference, and it
+/// frees the pointee when dropped, analogous to [`Box`].
+///
+/// Use [`MarkedNonNull`] instead when a non-owning, `Copy`-able view of a
+/// tagged pointer is all that's needed.
+pub struct MarkedBox<T, const N: usize> {
+    inner: MarkedNonNull<T, N>,
+}
+
+/********** impl Send + Sync ***********************************************************************/
+
+unsafe impl<T: Send, const N: usize> Send for MarkedBox<T, N> {}
+unsafe impl<T: Sync, const N: usize> Sync for MarkedBox<T, N> {}
+
+/********** impl inherent ***************************************************************************/
+
+impl<T, const N: usize> MarkedBox<T, N> {
+    /// Allocates memory for `value` and moves it there, returning an
+    /// unmarked [`MarkedBox`].
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self::from_box(Box::new(value))
+    }
+
+    /// Converts a `box`ed `value` into a [`MarkedBox`], without any tag set.
+    #[inline]
+    pub fn from_box(boxed: Box<T>) -> Self {
+        // SAFETY: `Box::into_raw` never returns a null pointer.
+        let non_null = unsafe { NonNull::new_unchecked(Box::into_raw(boxed)) };
+        Self { inner: MarkedNonNull::compose(non_null, 0) }
+    }
+
+    /// Consumes `self` and converts it back into a [`Box`], discarding the
+    /// tag.
+    #[inline]
+    pub fn into_box(self) -> Box<T> {
+        let ptr = self.inner.decompose_ptr();
+        mem::forget(self);
+        // SAFETY: `ptr` was created from a `Box::into_raw` pointer in
+        // `from_box`/`new` and has not been freed yet, since `self` was just
+        // forgotten rather than dropped.
+        unsafe { Box::from_raw(ptr.as_ptr()) }
+    }
+
+    /// Returns the currently stored tag.
+    #[inline]
+    pub fn tag(&self) -> usize {
+        self.inner.decompose_tag()
+    }
+
+    /// Clears the tag of `self` and replaces it with `tag`, returning the
+    /// previously stored tag.
+    #[inline]
+    pub fn set_tag(&mut self, tag: usize) -> usize {
+        let (ptr, prev) = self.inner.decompose();
+        self.inner = MarkedNonNull::compose(ptr, tag);
+        prev
+    }
+}
+
+/********** impl Deref *****************************************************************************/
+
+impl<T, const N: usize> Deref for MarkedBox<T, N> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: `inner` is a valid, owned allocation for the lifetime of
+        // `self`, and `decompose_ptr` strips the tag bits before the
+        // pointer is dereferenced.
+        unsafe { self.inner.decompose_ptr().as_ref() }
+    }
+}
+
+/********** impl DerefMut **************************************************************************/
+
+impl<T, const N: usize> DerefMut for MarkedBox<T, N> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: see `Deref::deref`; `self` is borrowed mutably, so no
+        // aliasing reference can exist.
+        unsafe { self.inner.decompose_ptr().as_mut() }
+    }
+}
+
+/********** impl Drop ******************************************************************************/
+
+impl<T, const N: usize> Drop for MarkedBox<T, N> {
+    #[inline]
+    fn drop(&mut self) {
+        let ptr = self.inner.decompose_ptr();
+        // SAFETY: `inner` owns a `Box::into_raw` allocation that has not
+        // been freed yet, since `MarkedBox` is the sole owner of it.
+        unsafe { drop(Box::from_raw(ptr.as_ptr())) };
+    }
+}
+
+/********** impl From (Box) ************************************************************************/
+
+impl<T, const N: usize> From<Box<T>> for MarkedBox<T, N> {
+    #[inline]
+    fn from(boxed: Box<T>) -> Self {
+        Self::from_box(boxed)
+    }
+}
+
+/********** impl Debug *****************************************************************************/
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for MarkedBox<T, N> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MarkedBox").field("value", &**self).field("tag", &self.tag()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    type MarkedBox<T> = super::MarkedBox<T, 2>;
+
+    #[test]
+    fn deref() {
+        let boxed = MarkedBox::new(1);
+        assert_eq!(*boxed, 1);
+    }
+
+    #[test]
+    fn deref_mut() {
+        let mut boxed = MarkedBox::new(1);
+        *boxed = 2;
+        assert_eq!(*boxed, 2);
+    }
+
+    #[test]
+    fn set_tag() {
+        let mut boxed = MarkedBox::new(1);
+        assert_eq!(boxed.tag(), 0);
+        assert_eq!(boxed.set_tag(0b11), 0);
+        assert_eq!(boxed.tag(), 0b11);
+        assert_eq!(*boxed, 1);
+    }
+
+    #[test]
+    fn into_box() {
+        let mut boxed = MarkedBox::new(1);
+        boxed.set_tag(0b11);
+        assert_eq!(*boxed.into_box(), 1);
+    }
+
+    #[test]
+    fn drops_pointee() {
+        struct DropCounter<'a>(&'a core::cell::Cell<usize>);
+        impl Drop for DropCounter<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let count = core::cell::Cell::new(0);
+        let boxed: MarkedBox<_> = MarkedBox::new(DropCounter(&count));
+        drop(boxed);
+        assert_eq!(count.get(), 1);
+    }
+}
@@ -0,0 +1,345 @@
+//! Implementation for [`AtomicMarkedPtr`].
+
+use core::fmt;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::MarkedPtr;
+
+/// A raw pointer type which can be safely shared between threads, which
+/// stores a pointer together with an additional `N` bit(s) of tag.
+///
+/// This type has the same in-memory representation as a `*mut T` and wraps
+/// an [`AtomicPtr<T>`], on which it atomically performs all operations. The
+/// tag occupies the same low-order bits as for [`MarkedPtr`], and every
+/// value handed out by this type goes through `AtomicPtr` without ever
+/// round-tripping through a bare `usize`, so the provenance of the stored
+/// pointer is preserved across `load`/`store`/`swap`/`compare_exchange`
+/// and, with the `strict_provenance` feature enabled, the `fetch_*` methods
+/// as well (these re-compose the stored pointer through
+/// [`MarkedPtr::decompose`]/[`MarkedPtr::compose`], which otherwise use
+/// integer casts). This makes `AtomicMarkedPtr` sound to use
+/// under the `strict_provenance` feature (see
+/// [`MarkedPtr`][crate::MarkedPtr]'s own documentation), unlike a
+/// hypothetical `AtomicUsize`-backed implementation, which would strip
+/// provenance on every load.
+#[repr(transparent)]
+pub struct AtomicMarkedPtr<T, const N: usize> {
+    inner: AtomicPtr<T>,
+}
+
+/********** impl Send + Sync ***********************************************************************/
+
+unsafe impl<T, const N: usize> Send for AtomicMarkedPtr<T, N> {}
+unsafe impl<T, const N: usize> Sync for AtomicMarkedPtr<T, N> {}
+
+/********** impl inherent **************************************************************************/
+
+impl<T, const N: usize> AtomicMarkedPtr<T, N> {
+    /// Creates a new unmarked `null` pointer.
+    #[inline]
+    pub const fn null() -> Self {
+        Self::new(MarkedPtr::null())
+    }
+
+    /// Creates a new [`AtomicMarkedPtr`] from an initial `ptr` value.
+    #[inline]
+    pub const fn new(ptr: MarkedPtr<T, N>) -> Self {
+        Self { inner: AtomicPtr::new(ptr.into_ptr()) }
+    }
+
+    /// Consumes `self` and returns the contained value.
+    #[inline]
+    pub fn into_inner(self) -> MarkedPtr<T, N> {
+        MarkedPtr::new(self.inner.into_inner())
+    }
+
+    /// Loads the current [`MarkedPtr`] value.
+    ///
+    /// `load` takes an [`Ordering`] argument, which describes the memory
+    /// ordering of this operation. Possible values are [`SeqCst`][seq_cst],
+    /// [`Acquire`][acquire] and [`Relaxed`][relaxed].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is [`Release`] or [`AcqRel`].
+    ///
+    /// [seq_cst]: Ordering::SeqCst
+    /// [acquire]: Ordering::Acquire
+    /// [relaxed]: Ordering::Relaxed
+    /// [`Release`]: Ordering::Release
+    /// [`AcqRel`]: Ordering::AcqRel
+    #[inline]
+    pub fn load(&self, order: Ordering) -> MarkedPtr<T, N> {
+        MarkedPtr::new(self.inner.load(order))
+    }
+
+    /// Stores a new `ptr` value.
+    ///
+    /// `store` takes an [`Ordering`] argument, which describes the memory
+    /// ordering of this operation. Possible values are [`SeqCst`][seq_cst],
+    /// [`Release`][release] and [`Relaxed`][relaxed].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is [`Acquire`] or [`AcqRel`].
+    ///
+    /// [seq_cst]: Ordering::SeqCst
+    /// [release]: Ordering::Release
+    /// [relaxed]: Ordering::Relaxed
+    /// [`Acquire`]: Ordering::Acquire
+    /// [`AcqRel`]: Ordering::AcqRel
+    #[inline]
+    pub fn store(&self, ptr: MarkedPtr<T, N>, order: Ordering) {
+        self.inner.store(ptr.into_ptr(), order);
+    }
+
+    /// Stores a new `ptr` value, returning the previous value.
+    #[inline]
+    pub fn swap(&self, ptr: MarkedPtr<T, N>, order: Ordering) -> MarkedPtr<T, N> {
+        MarkedPtr::new(self.inner.swap(ptr.into_ptr(), order))
+    }
+
+    /// Stores a new `new` value if the current value is the same as
+    /// `current`.
+    ///
+    /// The comparison is performed on the full composed pointer, i.e. the
+    /// address **and** its tag, so this method can also be used to implement
+    /// ABA-counter-style tagging schemes.
+    ///
+    /// `compare_exchange` takes two [`Ordering`] arguments to describe the
+    /// memory ordering of this operation. `success` describes the required
+    /// ordering if the operation succeeds, while `failure` describes the
+    /// required ordering when the operation fails.
+    #[inline]
+    pub fn compare_exchange(
+        &self,
+        current: MarkedPtr<T, N>,
+        new: MarkedPtr<T, N>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<MarkedPtr<T, N>, MarkedPtr<T, N>> {
+        self.inner
+            .compare_exchange(current.into_ptr(), new.into_ptr(), success, failure)
+            .map(MarkedPtr::new)
+            .map_err(MarkedPtr::new)
+    }
+
+    /// Stores a new `new` value if the current value is the same as
+    /// `current`.
+    ///
+    /// Unlike [`compare_exchange`][AtomicMarkedPtr::compare_exchange], this
+    /// method is permitted to spuriously fail even when the comparison
+    /// succeeds, which can result in more efficient code on some platforms.
+    /// The comparison is performed on the full composed pointer, as with
+    /// `compare_exchange`.
+    #[inline]
+    pub fn compare_exchange_weak(
+        &self,
+        current: MarkedPtr<T, N>,
+        new: MarkedPtr<T, N>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<MarkedPtr<T, N>, MarkedPtr<T, N>> {
+        self.inner
+            .compare_exchange_weak(current.into_ptr(), new.into_ptr(), success, failure)
+            .map(MarkedPtr::new)
+            .map_err(MarkedPtr::new)
+    }
+
+    /// Adds `value` to the current tag, leaving the pointer bits untouched.
+    ///
+    /// This method does not perform any checks, so it may overflow the tag
+    /// bits, in which case the overflowing bits are discarded rather than
+    /// carrying into the pointer bits.
+    ///
+    /// This operation is implemented as a compare-and-swap loop, since
+    /// `AtomicPtr` has no native fetch-add and a plain integer addition could
+    /// otherwise corrupt the stored pointer (or its provenance).
+    #[inline]
+    pub fn fetch_add(&self, value: usize, order: Ordering) -> MarkedPtr<T, N> {
+        self.fetch_update_tag(order, |tag| tag.wrapping_add(value))
+    }
+
+    /// Subtracts `value` from the current tag, leaving the pointer bits
+    /// untouched.
+    ///
+    /// This method does not perform any checks, so it may underflow the tag
+    /// bits, in which case the underflowing bits are discarded rather than
+    /// borrowing from the pointer bits.
+    ///
+    /// This operation is implemented as a compare-and-swap loop, since
+    /// `AtomicPtr` has no native fetch-sub and a plain integer subtraction
+    /// could otherwise corrupt the stored pointer (or its provenance).
+    #[inline]
+    pub fn fetch_sub(&self, value: usize, order: Ordering) -> MarkedPtr<T, N> {
+        self.fetch_update_tag(order, |tag| tag.wrapping_sub(value))
+    }
+
+    /// Performs a bitwise "or" of `value` with the current tag, leaving the
+    /// pointer bits untouched.
+    ///
+    /// `value` is masked with [`TAG_MASK`][MarkedPtr::TAG_MASK] before the
+    /// operation is applied. Like `fetch_add`/`fetch_sub`, this is
+    /// implemented as a compare-and-swap loop, since `AtomicPtr` has no
+    /// native fetch-or.
+    #[inline]
+    pub fn fetch_or(&self, value: usize, order: Ordering) -> MarkedPtr<T, N> {
+        let value = value & MarkedPtr::<T, N>::TAG_MASK;
+        self.fetch_update_tag(order, move |tag| tag | value)
+    }
+
+    /// Performs a bitwise "and" of `value` with the current tag, leaving the
+    /// pointer bits untouched.
+    ///
+    /// `value` is masked with [`TAG_MASK`][MarkedPtr::TAG_MASK] before the
+    /// operation is applied. Like `fetch_add`/`fetch_sub`, this is
+    /// implemented as a compare-and-swap loop, since `AtomicPtr` has no
+    /// native fetch-and.
+    #[inline]
+    pub fn fetch_and(&self, value: usize, order: Ordering) -> MarkedPtr<T, N> {
+        let value = value & MarkedPtr::<T, N>::TAG_MASK;
+        self.fetch_update_tag(order, move |tag| tag & value)
+    }
+
+    /// Updates the tag of the stored pointer by applying `func` to it,
+    /// retrying until the compare-and-swap succeeds, and returns the
+    /// previous value.
+    ///
+    /// Since the loop is driven by [`AtomicPtr::compare_exchange_weak`]
+    /// rather than a `usize`-based CAS, the pointer bits of each observed
+    /// value are carried through [`MarkedPtr::decompose`]/[`MarkedPtr::compose`]
+    /// untouched; their provenance is kept as well when the
+    /// `strict_provenance` feature is enabled.
+    #[inline]
+    fn fetch_update_tag(&self, order: Ordering, func: impl Fn(usize) -> usize) -> MarkedPtr<T, N> {
+        let mut prev = self.inner.load(Ordering::Relaxed);
+        loop {
+            let (ptr, tag) = MarkedPtr::<T, N>::new(prev).decompose();
+            let next = MarkedPtr::<T, N>::compose(ptr, func(tag)).into_ptr();
+            match self.inner.compare_exchange_weak(prev, next, order, Ordering::Relaxed) {
+                Ok(prev) => return MarkedPtr::new(prev),
+                Err(next_prev) => prev = next_prev,
+            }
+        }
+    }
+}
+
+/********** impl Default ***************************************************************************/
+
+impl<T, const N: usize> Default for AtomicMarkedPtr<T, N> {
+    #[inline]
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+/********** impl From (MarkedPtr) ******************************************************************/
+
+impl<T, const N: usize> From<MarkedPtr<T, N>> for AtomicMarkedPtr<T, N> {
+    #[inline]
+    fn from(ptr: MarkedPtr<T, N>) -> Self {
+        Self::new(ptr)
+    }
+}
+
+/********** impl From (*mut T) *********************************************************************/
+
+impl<T, const N: usize> From<*mut T> for AtomicMarkedPtr<T, N> {
+    #[inline]
+    fn from(ptr: *mut T) -> Self {
+        Self::new(MarkedPtr::new(ptr))
+    }
+}
+
+/********** impl Debug ******************************************************************************/
+
+impl<T, const N: usize> fmt::Debug for AtomicMarkedPtr<T, N> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ptr = self.load(Ordering::SeqCst);
+        f.debug_struct("AtomicMarkedPtr")
+            .field("ptr", &ptr.decompose_ptr())
+            .field("tag", &ptr.decompose_tag())
+            .finish()
+    }
+}
+
+/********** impl Pointer ****************************************************************************/
+
+impl<T, const N: usize> fmt::Pointer for AtomicMarkedPtr<T, N> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Pointer::fmt(&self.load(Ordering::SeqCst).decompose_ptr(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::Ordering;
+
+    use crate::MarkedPtr;
+
+    type AtomicMarkedPtr = crate::AtomicMarkedPtr<i32, 2>;
+
+    #[test]
+    fn load_store() {
+        let raw = &mut 1 as *mut i32;
+        let atomic = AtomicMarkedPtr::new(MarkedPtr::compose(raw, 0b11));
+        assert_eq!(atomic.load(Ordering::Relaxed).decompose(), (raw, 0b11));
+
+        atomic.store(MarkedPtr::compose(raw, 0b01), Ordering::Relaxed);
+        assert_eq!(atomic.load(Ordering::Relaxed).decompose(), (raw, 0b01));
+    }
+
+    #[test]
+    fn swap() {
+        let raw = &mut 1 as *mut i32;
+        let atomic = AtomicMarkedPtr::new(MarkedPtr::compose(raw, 0b11));
+        let prev = atomic.swap(MarkedPtr::compose(raw, 0b01), Ordering::Relaxed);
+        assert_eq!(prev.decompose(), (raw, 0b11));
+        assert_eq!(atomic.load(Ordering::Relaxed).decompose(), (raw, 0b01));
+    }
+
+    #[test]
+    fn compare_exchange() {
+        let raw = &mut 1 as *mut i32;
+        let atomic = AtomicMarkedPtr::new(MarkedPtr::compose(raw, 0b11));
+
+        let current = MarkedPtr::compose(raw, 0b11);
+        let new = MarkedPtr::compose(raw, 0b01);
+        assert_eq!(
+            atomic.compare_exchange(current, new, Ordering::Relaxed, Ordering::Relaxed),
+            Ok(current)
+        );
+        assert_eq!(
+            atomic.compare_exchange(current, new, Ordering::Relaxed, Ordering::Relaxed),
+            Err(new)
+        );
+    }
+
+    #[test]
+    fn fetch_add_sub() {
+        let raw = &mut 1 as *mut i32;
+        let atomic = AtomicMarkedPtr::new(MarkedPtr::compose(raw, 0b00));
+
+        let prev = atomic.fetch_add(0b01, Ordering::Relaxed);
+        assert_eq!(prev.decompose(), (raw, 0b00));
+        assert_eq!(atomic.load(Ordering::Relaxed).decompose(), (raw, 0b01));
+
+        let prev = atomic.fetch_sub(0b01, Ordering::Relaxed);
+        assert_eq!(prev.decompose(), (raw, 0b01));
+        assert_eq!(atomic.load(Ordering::Relaxed).decompose(), (raw, 0b00));
+    }
+
+    #[test]
+    fn fetch_or_and() {
+        let raw = &mut 1 as *mut i32;
+        let atomic = AtomicMarkedPtr::new(MarkedPtr::compose(raw, 0b01));
+
+        atomic.fetch_or(0b10, Ordering::Relaxed);
+        assert_eq!(atomic.load(Ordering::Relaxed).decompose(), (raw, 0b11));
+
+        atomic.fetch_and(0b10, Ordering::Relaxed);
+        assert_eq!(atomic.load(Ordering::Relaxed).decompose(), (raw, 0b10));
+    }
+}
@@ -5,7 +5,7 @@ use core::fmt;
 use core::hash::{Hash, Hasher};
 use core::ptr::{self, NonNull};
 
-use crate::{MarkedNonNull, MarkedPtr};
+use crate::{MarkedNonNull, MarkedPtr, Tag};
 
 /********** impl Clone ****************************************************************************/
 
@@ -45,6 +45,16 @@ impl<T, const N: usize> MarkedPtr<T, N> {
     /// Creates a [`MarkedPtr`] from the integer (numeric) representation of a
     /// potentially marked pointer.
     ///
+    /// # Provenance
+    ///
+    /// Since this method conjures a pointer from a plain `usize`, the
+    /// returned pointer carries no provenance. Under the `strict_provenance`
+    /// feature, dereferencing it is therefore undefined behaviour, even if
+    /// `val`'s address is valid and live; use
+    /// [`from_usize_with_provenance`][MarkedPtr::from_usize_with_provenance]
+    /// to reattach the provenance of an existing pointer into the same
+    /// allocation instead.
+    ///
     /// # Examples
     ///
     /// ```
@@ -60,12 +70,28 @@ impl<T, const N: usize> MarkedPtr<T, N> {
         Self { inner: val as *mut _ }
     }
 
+    /// Creates a [`MarkedPtr`] with address `val`, reattaching the
+    /// provenance of `source`.
+    ///
+    /// This is the strict-provenance-correct counterpart to
+    /// [`from_usize`][MarkedPtr::from_usize]: instead of conjuring a pointer
+    /// with no provenance out of thin air, the returned pointer inherits its
+    /// provenance from `source`, while its address (pointer bits and tag
+    /// bits alike) is taken from `val`. `source` is typically a pointer into
+    /// the same allocation that `val`'s address refers to, e.g. one
+    /// previously obtained from [`into_usize`][MarkedPtr::into_usize].
+    #[cfg(feature = "strict_provenance")]
+    #[inline]
+    pub fn from_usize_with_provenance(val: usize, source: *mut T) -> Self {
+        Self { inner: source.with_addr(val) }
+    }
+
     /// Composes a new [`MarkedPtr`] from a raw `ptr` and a `tag` value.
     ///
     /// The supplied `ptr` is assumed to be well-aligned (i.e. has no tag bits
     /// set), so this function may lead to unexpected results when this is not
-    /// the case. 
-    /// 
+    /// the case.
+    ///
     /// # Examples
     ///
     /// ```
@@ -80,12 +106,32 @@ impl<T, const N: usize> MarkedPtr<T, N> {
     /// let ptr = MarkedPtr::compose(raw, 0b101);
     /// assert_eq!(ptr.decompose(), (raw, 0b01));
     /// ```
+    #[cfg(not(feature = "strict_provenance"))]
     #[inline]
     pub fn compose(ptr: *mut T, tag: usize) -> Self {
         crate::assert_alignment::<T, N>();
         Self::new(crate::compose(ptr, tag, Self::TAG_BITS))
     }
 
+    /// Composes a new [`MarkedPtr`] from a raw `ptr` and a `tag` value.
+    ///
+    /// The supplied `ptr` is assumed to be well-aligned (i.e. has no tag bits
+    /// set), so this function may lead to unexpected results when this is not
+    /// the case.
+    ///
+    /// Unlike the non-`strict_provenance` implementation, this keeps the
+    /// provenance of `ptr` intact by deriving the composed pointer with
+    /// [`map_addr`][pointer::map_addr] rather than round-tripping through a
+    /// `usize`.
+    ///
+    /// [pointer::map_addr]: https://doc.rust-lang.org/std/primitive.pointer.html#method.map_addr
+    #[cfg(feature = "strict_provenance")]
+    #[inline]
+    pub fn compose(ptr: *mut T, tag: usize) -> Self {
+        crate::assert_alignment::<T, N>();
+        Self::new(ptr.map_addr(|addr| (addr & Self::POINTER_MASK) | (tag & Self::TAG_MASK)))
+    }
+
     /// Returns the inner pointer *as is*, meaning any potential tag is **not**
     /// stripped.
     ///
@@ -105,11 +151,25 @@ impl<T, const N: usize> MarkedPtr<T, N> {
     }
 
     /// Returns the integer representation of the pointer with its tag.
+    #[cfg(not(feature = "strict_provenance"))]
     #[inline]
     pub fn into_usize(self) -> usize {
         self.inner as usize
     }
 
+    /// Returns the integer representation of the pointer with its tag.
+    ///
+    /// This only exposes the pointer's address (via
+    /// [`addr`][pointer::addr]), so it does not by itself invalidate the
+    /// pointer's provenance.
+    ///
+    /// [pointer::addr]: https://doc.rust-lang.org/std/primitive.pointer.html#method.addr
+    #[cfg(feature = "strict_provenance")]
+    #[inline]
+    pub fn into_usize(self) -> usize {
+        self.inner.addr()
+    }
+
     /// Returns `true` if the [`MarkedPtr`] is null.
     ///
     /// This is equivalent to calling `marked_ptr.decompose_ptr().is_null()`.
@@ -184,41 +244,139 @@ impl<T, const N: usize> MarkedPtr<T, N> {
     /// This method does not perform any checks, so it may overflow the tag
     /// bits, result in a pointer to a different value, a null pointer or an
     /// unaligned pointer.
+    #[cfg(not(feature = "strict_provenance"))]
     #[inline]
     pub fn add_tag(self, value: usize) -> Self {
         Self::from_usize(self.into_usize() + value)
     }
 
+    /// Adds `value` to the current tag without regard for the previous value.
+    ///
+    /// This method does not perform any checks, so it may overflow the tag
+    /// bits, result in a pointer to a different value, a null pointer or an
+    /// unaligned pointer.
+    ///
+    /// The provenance of `self` is carried over to the result via
+    /// [`from_usize_with_provenance`][MarkedPtr::from_usize_with_provenance],
+    /// rather than being discarded as a plain [`from_usize`][MarkedPtr::from_usize]
+    /// round-trip would.
+    #[cfg(feature = "strict_provenance")]
+    #[inline]
+    pub fn add_tag(self, value: usize) -> Self {
+        Self::from_usize_with_provenance(self.into_usize() + value, self.inner)
+    }
+
     /// Subtracts `value` to the current tag without regard for the previous
     /// value.
     ///
     /// This method does not perform any checks, so it may underflow the tag
     /// bits, result in a pointer to a different value, a null pointer or an
     /// unaligned pointer.
+    #[cfg(not(feature = "strict_provenance"))]
     #[inline]
     pub fn sub_tag(self, value: usize) -> Self {
         Self::from_usize(self.into_usize() - value)
     }
 
+    /// Subtracts `value` to the current tag without regard for the previous
+    /// value.
+    ///
+    /// This method does not perform any checks, so it may underflow the tag
+    /// bits, result in a pointer to a different value, a null pointer or an
+    /// unaligned pointer.
+    ///
+    /// The provenance of `self` is carried over to the result via
+    /// [`from_usize_with_provenance`][MarkedPtr::from_usize_with_provenance],
+    /// rather than being discarded as a plain [`from_usize`][MarkedPtr::from_usize]
+    /// round-trip would.
+    #[cfg(feature = "strict_provenance")]
+    #[inline]
+    pub fn sub_tag(self, value: usize) -> Self {
+        Self::from_usize_with_provenance(self.into_usize() - value, self.inner)
+    }
+
     /// Decomposes the [`MarkedPtr`], returning the separated raw pointer and
     /// its tag.
+    #[cfg(not(feature = "strict_provenance"))]
     #[inline]
     pub fn decompose(self) -> (*mut T, usize) {
         crate::decompose::<T>(self.inner as usize, Self::TAG_BITS)
     }
 
+    /// Decomposes the [`MarkedPtr`], returning the separated raw pointer and
+    /// its tag.
+    #[cfg(feature = "strict_provenance")]
+    #[inline]
+    pub fn decompose(self) -> (*mut T, usize) {
+        (self.decompose_ptr(), self.decompose_tag())
+    }
+
     /// Decomposes the [`MarkedPtr`], returning only the separated raw pointer.
+    #[cfg(not(feature = "strict_provenance"))]
     #[inline]
     pub fn decompose_ptr(self) -> *mut T {
         crate::decompose_ptr::<T>(self.inner as usize, Self::TAG_BITS)
     }
 
+    /// Decomposes the [`MarkedPtr`], returning only the separated raw
+    /// pointer.
+    ///
+    /// This keeps the provenance of `self` intact by deriving the result
+    /// with [`map_addr`][pointer::map_addr] rather than round-tripping
+    /// through a `usize`.
+    ///
+    /// [pointer::map_addr]: https://doc.rust-lang.org/std/primitive.pointer.html#method.map_addr
+    #[cfg(feature = "strict_provenance")]
+    #[inline]
+    pub fn decompose_ptr(self) -> *mut T {
+        self.inner.map_addr(|addr| addr & Self::POINTER_MASK)
+    }
+
     /// Decomposes the [`MarkedPtr`], returning only the separated tag value.
+    #[cfg(not(feature = "strict_provenance"))]
     #[inline]
     pub fn decompose_tag(self) -> usize {
         crate::decompose_tag::<T>(self.inner as usize, Self::TAG_BITS)
     }
 
+    /// Decomposes the [`MarkedPtr`], returning only the separated tag value.
+    #[cfg(feature = "strict_provenance")]
+    #[inline]
+    pub fn decompose_tag(self) -> usize {
+        self.inner.addr() & Self::TAG_MASK
+    }
+
+    /// Composes a new [`MarkedPtr`] from a raw `ptr` and a typed `tag`.
+    ///
+    /// This is the typed counterpart to [`compose`][MarkedPtr::compose]:
+    /// instead of a bare `usize`, any type implementing [`Tag`] can be
+    /// stored in the tag bits, as long as it fits within the `N` bits
+    /// available to this pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `G::BITS` is greater than `N`.
+    #[inline]
+    pub fn compose_tag<G: Tag>(ptr: *mut T, tag: G) -> Self {
+        assert!(G::BITS <= N as u32, "`tag` requires more bits than are available");
+        Self::compose(ptr, tag.into_usize())
+    }
+
+    /// Decomposes the [`MarkedPtr`], returning the separated raw pointer and
+    /// its tag, reconstructed as a `G`.
+    ///
+    /// # Safety
+    ///
+    /// The tag bits of `self` must previously have been set through
+    /// [`compose_tag`][MarkedPtr::compose_tag] (or an equivalent encoding)
+    /// for the same type `G`, as required by [`Tag::from_usize`].
+    #[inline]
+    pub unsafe fn decompose_typed<G: Tag>(self) -> (*mut T, G) {
+        assert!(G::BITS <= N as u32, "`tag` requires more bits than are available");
+        let (ptr, tag) = self.decompose();
+        (ptr, G::from_usize(tag))
+    }
+
     /// Decomposes the marked pointer, returning an optional reference and the
     /// separated tag.
     ///
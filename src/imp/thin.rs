@@ -0,0 +1,221 @@
+//! Implementation for [`MarkedThinPtr`].
+
+use core::cmp;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use ptr_meta::Pointee;
+
+/// A marked pointer type for thin, possibly `?Sized` pointees.
+///
+/// [`MarkedPtr<T, N>`][crate::MarkedPtr] stores its tag in the low-order
+/// bits of a `*mut T` and derives the number of bits that are safe to use
+/// from `align_of::<T>()`, which requires `T: Sized`. Extern types and other
+/// thin-but-unsized pointees have no statically known alignment, so
+/// `MarkedThinPtr` instead accepts any `T: ?Sized` whose pointer metadata is
+/// zero-sized (i.e. `T::Metadata = ()`, as attested by [`Pointee`]) and
+/// takes the pointee's alignment as an explicit argument rather than one
+/// derived from `align_of`.
+pub struct MarkedThinPtr<T, const N: usize>
+where
+    T: ?Sized + Pointee<Metadata = ()>,
+{
+    inner: *mut T,
+}
+
+/********** impl Clone *****************************************************************************/
+
+impl<T, const N: usize> Clone for MarkedThinPtr<T, N>
+where
+    T: ?Sized + Pointee<Metadata = ()>,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/********** impl Copy ******************************************************************************/
+
+impl<T, const N: usize> Copy for MarkedThinPtr<T, N> where T: ?Sized + Pointee<Metadata = ()> {}
+
+/********** impl inherent **************************************************************************/
+
+impl<T, const N: usize> MarkedThinPtr<T, N>
+where
+    T: ?Sized + Pointee<Metadata = ()>,
+{
+    /// The number of available mark bits for this type.
+    pub const TAG_BITS: usize = N;
+    /// The bitmask for the lower markable bits.
+    ///
+    /// Unlike [`MarkedPtr::TAG_MASK`][crate::MarkedPtr::TAG_MASK], this does
+    /// not take the pointee's alignment into account, since it cannot
+    /// generally be determined for a `T: ?Sized`; callers of
+    /// [`compose`][MarkedThinPtr::compose] are responsible for passing an
+    /// `align` that actually guarantees `TAG_BITS` zeroed low-order bits.
+    pub const TAG_MASK: usize = match Self::TAG_BITS >= usize::BITS as usize {
+        true => usize::MAX,
+        false => (1 << Self::TAG_BITS) - 1,
+    };
+    /// The bitmask for the (higher) address bits.
+    pub const POINTER_MASK: usize = !Self::TAG_MASK;
+
+    /// Creates a new unmarked `null` pointer.
+    #[inline]
+    pub fn null() -> Self {
+        Self::new(ptr_meta::from_raw_parts_mut(core::ptr::null_mut(), ()))
+    }
+
+    /// Creates a new unmarked [`MarkedThinPtr`].
+    #[inline]
+    pub const fn new(ptr: *mut T) -> Self {
+        Self { inner: ptr }
+    }
+
+    /// Composes a new [`MarkedThinPtr`] from a raw `ptr` and a `tag` value.
+    ///
+    /// `align` must be the pointee's actual alignment (e.g. obtained from
+    /// the FFI declaration of an extern type); the supplied `ptr` is assumed
+    /// to already be aligned to it, i.e. to have no tag bits set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two, or does not provide at least
+    /// `TAG_BITS` zeroed low-order bits.
+    #[inline]
+    pub fn compose(ptr: *mut T, tag: usize, align: usize) -> Self {
+        assert!(align.is_power_of_two(), "`align` must be a power of two");
+        assert!(
+            align.trailing_zeros() as usize >= Self::TAG_BITS,
+            "`align` does not provide enough bits for the tag"
+        );
+
+        let (data, ()) = ptr_meta::to_raw_parts_mut(ptr);
+        let addr = (data as usize & Self::POINTER_MASK) | (tag & Self::TAG_MASK);
+        Self::new(ptr_meta::from_raw_parts_mut(addr as *mut (), ()))
+    }
+
+    /// Decomposes the [`MarkedThinPtr`], returning the separated raw pointer
+    /// and its tag.
+    #[inline]
+    pub fn decompose(self) -> (*mut T, usize) {
+        (self.decompose_ptr(), self.decompose_tag())
+    }
+
+    /// Decomposes the [`MarkedThinPtr`], returning only the separated raw
+    /// pointer.
+    #[inline]
+    pub fn decompose_ptr(self) -> *mut T {
+        let (data, ()) = ptr_meta::to_raw_parts_mut(self.inner);
+        let addr = data as usize & Self::POINTER_MASK;
+        ptr_meta::from_raw_parts_mut(addr as *mut (), ())
+    }
+
+    /// Decomposes the [`MarkedThinPtr`], returning only the separated tag
+    /// value.
+    #[inline]
+    pub fn decompose_tag(self) -> usize {
+        let (data, ()) = ptr_meta::to_raw_parts_mut(self.inner);
+        data as usize & Self::TAG_MASK
+    }
+}
+
+/********** impl Default ***************************************************************************/
+
+impl<T, const N: usize> Default for MarkedThinPtr<T, N>
+where
+    T: ?Sized + Pointee<Metadata = ()>,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+/********** impl Debug *****************************************************************************/
+
+impl<T, const N: usize> fmt::Debug for MarkedThinPtr<T, N>
+where
+    T: ?Sized + Pointee<Metadata = ()>,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MarkedThinPtr")
+            .field("ptr", &self.decompose_ptr())
+            .field("tag", &self.decompose_tag())
+            .finish()
+    }
+}
+
+/********** impl PartialEq *************************************************************************/
+
+impl<T, const N: usize> PartialEq for MarkedThinPtr<T, N>
+where
+    T: ?Sized + Pointee<Metadata = ()>,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        let (lhs, ()) = ptr_meta::to_raw_parts_mut(self.inner);
+        let (rhs, ()) = ptr_meta::to_raw_parts_mut(other.inner);
+        lhs.eq(&rhs)
+    }
+}
+
+/********** impl Eq ********************************************************************************/
+
+impl<T, const N: usize> Eq for MarkedThinPtr<T, N> where T: ?Sized + Pointee<Metadata = ()> {}
+
+/********** impl PartialOrd ************************************************************************/
+
+impl<T, const N: usize> PartialOrd for MarkedThinPtr<T, N>
+where
+    T: ?Sized + Pointee<Metadata = ()>,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        let (lhs, ()) = ptr_meta::to_raw_parts_mut(self.inner);
+        let (rhs, ()) = ptr_meta::to_raw_parts_mut(other.inner);
+        lhs.partial_cmp(&rhs)
+    }
+}
+
+/********** impl Hash ******************************************************************************/
+
+impl<T, const N: usize> Hash for MarkedThinPtr<T, N>
+where
+    T: ?Sized + Pointee<Metadata = ()>,
+{
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let (data, ()) = ptr_meta::to_raw_parts_mut(self.inner);
+        data.hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr;
+
+    type MarkedThinPtr = crate::MarkedThinPtr<i32, 2>;
+
+    #[test]
+    fn compose_decompose() {
+        let raw = &mut 1 as *mut i32;
+        let ptr = MarkedThinPtr::compose(raw, 0b11, 4);
+        assert_eq!(ptr.decompose(), (raw, 0b11));
+    }
+
+    #[test]
+    #[should_panic]
+    fn align_too_small() {
+        let raw = &mut 1 as *mut i32;
+        let _ptr = MarkedThinPtr::compose(raw, 0b11, 2);
+    }
+
+    #[test]
+    fn null() {
+        let ptr = MarkedThinPtr::null();
+        assert_eq!(ptr.decompose(), (ptr::null_mut(), 0));
+    }
+}
@@ -0,0 +1,156 @@
+//! Typed tag support for [`MarkedPtr`][crate::MarkedPtr] and
+//! [`MarkedNonNull`][crate::MarkedNonNull] via the [`Tag`] trait.
+
+/// A trait for types that can be stored in the tag bits of a marked pointer.
+///
+/// Implementing this trait allows a small, typed value (e.g. a three-variant
+/// state enum) to be composed into and decomposed out of the low-order tag
+/// bits of a marked pointer, instead of callers having to hand-roll the bit
+/// encoding themselves.
+///
+/// Field-less enums can derive a correct, non-overlapping implementation
+/// with the [`impl_tag!`] macro rather than implementing this trait by hand.
+///
+/// # Safety
+///
+/// `from_usize` must be able to reconstruct any value previously produced by
+/// `into_usize` for the lowest `BITS` bits of its argument. Implementations
+/// are not required to handle arbitrary `bits` values gracefully (they may
+/// panic or return a bogus value), since callers of `from_usize` are
+/// themselves required to only pass bits that originated from `into_usize`.
+pub trait Tag: Copy {
+    /// The number of low-order bits required to represent all values of
+    /// this type.
+    const BITS: u32;
+
+    /// Converts `self` into its `usize` representation.
+    ///
+    /// The returned value must fit within the lowest `BITS` bits, i.e. it
+    /// must be less than `1 << BITS`.
+    fn into_usize(self) -> usize;
+
+    /// Reconstructs a `Self` from its `usize` representation.
+    ///
+    /// # Safety
+    ///
+    /// `bits` must be a value that was previously returned by
+    /// [`into_usize`][Tag::into_usize] for some value of `Self`.
+    unsafe fn from_usize(bits: usize) -> Self;
+}
+
+/// Computes the number of bits required to represent `count` distinct,
+/// consecutively numbered values (i.e. `ceil(log2(count))`).
+#[doc(hidden)]
+pub const fn bits_for(count: usize) -> u32 {
+    match count {
+        0 | 1 => 0,
+        count => usize::BITS - (count - 1).leading_zeros(),
+    }
+}
+
+/// Generates a [`Tag`] implementation for a field-less enum.
+///
+/// Each variant is assigned a distinct, consecutive discriminant starting at
+/// `0`, so variants can never overlap in their bit pattern, and `BITS` is
+/// computed as the smallest number of bits required to represent all
+/// variants.
+///
+/// # Examples
+///
+/// ```
+/// use conquer_pointer::{impl_tag, Tag};
+///
+/// impl_tag! {
+///     enum Status {
+///         Clean,
+///         Marked,
+///         Deleted,
+///     }
+/// }
+///
+/// assert_eq!(Status::BITS, 2);
+///
+/// type MarkedPtr = conquer_pointer::MarkedPtr<i32, 2>;
+///
+/// let raw = &mut 1 as *mut i32;
+/// let ptr = MarkedPtr::compose_tag(raw, Status::Marked);
+/// assert_eq!(unsafe { ptr.decompose_typed::<Status>() }, (raw, Status::Marked));
+/// ```
+#[macro_export]
+macro_rules! impl_tag {
+    ($(#[$attr:meta])* $vis:vis enum $name:ident { $($variant:ident),+ $(,)? }) => {
+        $(#[$attr])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+        $vis enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            const VARIANT_COUNT: usize = $crate::impl_tag!(@count $($variant),+);
+        }
+
+        impl $crate::Tag for $name {
+            const BITS: u32 = $crate::tag::bits_for(<$name>::VARIANT_COUNT);
+
+            #[inline]
+            fn into_usize(self) -> usize {
+                self as usize
+            }
+
+            #[inline]
+            unsafe fn from_usize(bits: usize) -> Self {
+                let mut idx = 0usize;
+                $(
+                    if bits == idx {
+                        return $name::$variant;
+                    }
+                    idx += 1;
+                )+
+                let _ = idx;
+                unreachable!("invalid tag bits for `{}`", stringify!($name))
+            }
+        }
+    };
+    (@count $($variant:ident),+) => {
+        <[()]>::len(&[$($crate::impl_tag!(@unit $variant)),+])
+    };
+    (@unit $variant:ident) => { () };
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr;
+
+    use crate::Tag;
+
+    impl_tag! {
+        enum Status {
+            Clean,
+            Marked,
+            Deleted,
+        }
+    }
+
+    #[test]
+    fn bits() {
+        assert_eq!(Status::BITS, 2);
+    }
+
+    #[test]
+    fn round_trip() {
+        type MarkedPtr = crate::MarkedPtr<i32, 2>;
+
+        let raw = &mut 1 as *mut i32;
+        for status in [Status::Clean, Status::Marked, Status::Deleted] {
+            let ptr = MarkedPtr::compose_tag(raw, status);
+            assert_eq!(unsafe { ptr.decompose_typed::<Status>() }, (raw, status));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_many_bits() {
+        type MarkedPtr = crate::MarkedPtr<i32, 1>;
+        let _ptr = MarkedPtr::compose_tag(ptr::null_mut(), Status::Deleted);
+    }
+}